@@ -1,19 +1,73 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use actix_web::{delete, get, HttpResponse, post, Scope, web};
+use actix_web::{delete, get, HttpRequest, HttpResponse, post, Scope, web};
 use actix_web::web::{Bytes, Data, Json, Query};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
-use crate::repository::data_repository::DataRepository;
+use crate::repository::checksum::{Checksum, ChecksumAlgorithm};
+use crate::repository::data_repository::{DataRepository, ReadError};
+use crate::repository::encryption;
+
+const DEFAULT_POLL_TIMEOUT_MS: u64 = 30_000;
+const CHECKSUM_HEADER_PREFIX: &str = "x-checksum-";
+const ENCRYPTION_KEY_HEADER: &str = "x-encryption-key";
+
+/// Reads the `x-encryption-key` header, if present, and base64-decodes it
+/// into a fixed-size AEAD key. `Err(())` means the header was present but
+/// malformed.
+fn parse_encryption_key_header(request: &HttpRequest) -> Result<Option<[u8; encryption::KEY_LEN]>, ()> {
+    match request.headers().get(ENCRYPTION_KEY_HEADER) {
+        None => Ok(None),
+        Some(value) => {
+            let encoded = value.to_str().map_err(|_| ())?;
+            let decoded = base64::engine::general_purpose::STANDARD_NO_PAD.decode(encoded).map_err(|_| ())?;
+            let key = <[u8; encryption::KEY_LEN]>::try_from(decoded.as_slice()).map_err(|_| ())?;
+            Ok(Some(key))
+        }
+    }
+}
+
+fn read_error_response(error: ReadError) -> HttpResponse {
+    match error {
+        ReadError::NotFound => HttpResponse::NotFound().body(""),
+        ReadError::KeyRequired => HttpResponse::Forbidden().body("")
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct ReadQuery {
-    key: String
+    key: String,
+    verify: Option<bool>
+}
+
+/// Scans request headers for an `x-checksum-<algo>` entry and, if present,
+/// parses the algorithm and hex-decodes the expected digest.
+///
+/// Returns `Ok(None)` when no checksum header is present, `Ok(Some(_))` when
+/// one was found and understood, and `Err(_)` when the algorithm or the
+/// digest encoding is invalid.
+fn parse_checksum_header(request: &HttpRequest) -> Result<Option<Checksum>, ()> {
+    for (name, value) in request.headers().iter() {
+        let name = name.as_str().to_ascii_lowercase();
+        if let Some(suffix) = name.strip_prefix(CHECKSUM_HEADER_PREFIX) {
+            let algorithm = ChecksumAlgorithm::from_header_suffix(suffix).ok_or(())?;
+            let digest_hex = value.to_str().map_err(|_| ())?;
+            let digest = hex::decode(digest_hex).map_err(|_| ())?;
+            return Ok(Some(Checksum { algorithm, digest }));
+        }
+    }
+    Ok(None)
+}
+
+fn checksum_header_value(checksum: &Checksum) -> (String, String) {
+    (format!("{CHECKSUM_HEADER_PREFIX}{}", checksum.algorithm.header_suffix()), hex::encode(&checksum.digest))
 }
 
 #[derive(Debug, Deserialize)]
 pub struct WriteQuery {
     key: String,
-    lifetime: Option<u32>
+    lifetime: Option<u32>,
+    ttl_ms: Option<u64>
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,22 +80,79 @@ pub struct KeysReturn {
     keys: Vec<String>
 }
 
+#[derive(Debug, Deserialize)]
+pub struct BatchReadRequest {
+    keys: Vec<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchWriteItem {
+    key: String,
+    value: String,
+    lifetime: Option<u32>,
+    ttl_ms: Option<u64>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchWriteRequest {
+    items: Vec<BatchWriteItem>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchRemoveRequest {
+    keys: Vec<String>
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollQuery {
+    key: String,
+    since: Option<u64>,
+    timeout_ms: Option<u64>
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollResult {
+    value: Option<String>,
+    version: u64
+}
+
+fn respond_with_value(value: Vec<u8>, checksum: Option<Checksum>, verify: bool) -> HttpResponse {
+    if verify {
+        if let Some(checksum) = &checksum {
+            if !checksum.matches(&value) {
+                return HttpResponse::InternalServerError().body("checksum mismatch");
+            }
+        }
+    }
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/octet-stream");
+    if let Some(checksum) = &checksum {
+        let (name, value) = checksum_header_value(checksum);
+        response.insert_header((name, value));
+    }
+    response.body(value)
+}
+
 #[get("/read")]
-pub async fn read(query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
-    match DataRepository::read(data.as_ref().clone(), &query.key).await{
-        None => { HttpResponse::NotFound().body("") }
-        Some(v) => { HttpResponse::Ok()
-            .content_type("application/octet-stream")
-            .body(v) }
+pub async fn read(request: HttpRequest, query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    match DataRepository::read(data.as_ref().clone(), &query.key, client_key).await{
+        Err(e) => read_error_response(e),
+        Ok((v, checksum)) => respond_with_value(v, checksum, query.verify.unwrap_or(false))
     }
 }
 #[get("/safe-read")]
-pub async fn safe_read(query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
-    match data.safe_read(&query.key).await{
-        None => { HttpResponse::NotFound().body("") }
-        Some(v) => { HttpResponse::Ok()
-            .content_type("application/octet-stream")
-            .body(v) }
+pub async fn safe_read(request: HttpRequest, query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    match data.safe_read(&query.key, client_key).await{
+        Err(e) => read_error_response(e),
+        Ok((v, checksum)) => respond_with_value(v, checksum, query.verify.unwrap_or(false))
     }
 }
 
@@ -62,10 +173,10 @@ pub async fn remove(query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) ->
         .body("")
 }
 
-fn handle_utf8(opt: Option<Vec<u8>>) -> HttpResponse {
-    match opt {
-        None => { HttpResponse::NotFound().body("") }
-        Some(v) => {
+fn handle_utf8(result: Result<Vec<u8>, ReadError>) -> HttpResponse {
+    match result {
+        Err(e) => read_error_response(e),
+        Ok(v) => {
             match String::from_utf8(v) {
                 Ok(string) => HttpResponse::Ok()
                     .content_type("text/plain")
@@ -78,12 +189,20 @@ fn handle_utf8(opt: Option<Vec<u8>>) -> HttpResponse {
 }
 
 #[get("/read-string")]
-pub async fn read_string(query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
-    handle_utf8(DataRepository::read(data.as_ref().clone(), &query.key).await)
+pub async fn read_string(request: HttpRequest, query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    handle_utf8(DataRepository::read(data.as_ref().clone(), &query.key, client_key).await.map(|(v, _)| v))
 }
 #[get("/safe-read-string")]
-pub async fn safe_read_string(query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
-    handle_utf8(data.safe_read(&query.key).await)
+pub async fn safe_read_string(request: HttpRequest, query: Query<ReadQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    handle_utf8(data.safe_read(&query.key, client_key).await.map(|(v, _)| v))
 }
 
 #[get("/all-keys")]
@@ -134,26 +253,115 @@ pub async fn load_json(limit: Query<LimitQuery>, dump_data: Json<HashMap<String,
 }
 
 #[post("/write")]
-pub async fn write(query: Query<WriteQuery>, body: Bytes, data: Data<Arc<DataRepository>>) -> HttpResponse {
+pub async fn write(request: HttpRequest, query: Query<WriteQuery>, body: Bytes, data: Data<Arc<DataRepository>>) -> HttpResponse {
     let limit = match query.lifetime {
         Some(v) => if v == 0 { u16::MAX as u32 } else { v },
         None => u16::MAX as u32
     };
-    data.write(query.key.clone(), body.to_vec(), limit).await;
+    let expected = match parse_checksum_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-checksum-<algo> header")
+    };
+    let checksum = match expected {
+        Some(expected) if !expected.matches(&body) => return HttpResponse::BadRequest().body("checksum mismatch"),
+        Some(expected) => Some(expected),
+        None => None
+    };
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    data.write(query.key.clone(), body.to_vec(), limit, query.ttl_ms, checksum, client_key).await;
     HttpResponse::Ok().body("")
 }
 
 #[post("/write-string")]
-pub async fn write_string(query: Query<WriteQuery>, body: String, data: Data<Arc<DataRepository>>) -> HttpResponse {
+pub async fn write_string(request: HttpRequest, query: Query<WriteQuery>, body: String, data: Data<Arc<DataRepository>>) -> HttpResponse {
     let limit = match query.lifetime {
         Some(v) => if v == 0 { u16::MAX as u32 } else { v },
         None => u16::MAX as u32
     };
-    data.write_string(query.key.clone(), body, limit).await;
+    let expected = match parse_checksum_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-checksum-<algo> header")
+    };
+    let checksum = match expected {
+        Some(expected) if !expected.matches(body.as_bytes()) => return HttpResponse::BadRequest().body("checksum mismatch"),
+        Some(expected) => Some(expected),
+        None => None
+    };
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    data.write_string(query.key.clone(), body, limit, query.ttl_ms, checksum, client_key).await;
     HttpResponse::Ok()
         .body("")
 }
 
+#[post("/batch-read")]
+pub async fn batch_read(request: HttpRequest, body: Json<BatchReadRequest>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    let values = data.batch_read(&body.keys, client_key).await;
+    let encoded: HashMap<String, Option<String>> = values.into_iter()
+        .map(|(key, value)| (key, value.map(|v| base64::engine::general_purpose::STANDARD_NO_PAD.encode(v))))
+        .collect();
+    HttpResponse::Ok().json(encoded)
+}
+
+#[post("/batch-write")]
+pub async fn batch_write(request: HttpRequest, body: Json<BatchWriteRequest>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    let mut items = Vec::with_capacity(body.items.len());
+    for item in &body.items {
+        let decoded = match base64::engine::general_purpose::STANDARD_NO_PAD.decode(&item.value) {
+            Ok(v) => v,
+            Err(_) => return HttpResponse::BadRequest().body("")
+        };
+        let limit = match item.lifetime {
+            Some(v) => if v == 0 { u16::MAX as u32 } else { v },
+            None => u16::MAX as u32
+        };
+        items.push((item.key.clone(), decoded, limit, item.ttl_ms));
+    }
+    let written = data.batch_write(items, client_key).await;
+    HttpResponse::Ok().json(written)
+}
+
+#[post("/batch-remove")]
+pub async fn batch_remove(body: Json<BatchRemoveRequest>, data: Data<Arc<DataRepository>>) -> Json<HashMap<String, bool>> {
+    let removed = data.batch_remove(&body.keys).await;
+    Json(removed)
+}
+
+#[get("/metrics")]
+pub async fn metrics(data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let body = data.render_metrics().await;
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
+
+#[get("/poll")]
+pub async fn poll(request: HttpRequest, query: Query<PollQuery>, data: Data<Arc<DataRepository>>) -> HttpResponse {
+    let client_key = match parse_encryption_key_header(&request) {
+        Ok(v) => v,
+        Err(_) => return HttpResponse::BadRequest().body("invalid x-encryption-key header")
+    };
+    let timeout = std::time::Duration::from_millis(query.timeout_ms.unwrap_or(DEFAULT_POLL_TIMEOUT_MS));
+    let (value, version) = data.poll(&query.key, query.since, timeout, client_key).await;
+    HttpResponse::Ok().json(PollResult {
+        value: value.map(|v| base64::engine::general_purpose::STANDARD_NO_PAD.encode(v)),
+        version
+    })
+}
+
 pub fn map() -> Scope {
     web::scope("")
         .service(read)
@@ -168,4 +376,9 @@ pub fn map() -> Scope {
         .service(load)
         .service(write)
         .service(write_string)
+        .service(batch_read)
+        .service(batch_write)
+        .service(batch_remove)
+        .service(poll)
+        .service(metrics)
 }
\ No newline at end of file
@@ -1,9 +1,23 @@
 use serde::Deserialize;
 
+fn default_backend() -> String {
+    "memory".to_string()
+}
+
+fn default_average_chunk_size_bits() -> u32 {
+    13
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ApplicationConfig {
     pub log_level: String,
     pub partition_count: u16,
     pub regex_partition_count: u16,
     pub regex_partition_capacity: u16,
-}
\ No newline at end of file
+    #[serde(default = "default_backend")]
+    pub backend: String,
+    pub backend_path: Option<String>,
+    #[serde(default = "default_average_chunk_size_bits")]
+    pub average_chunk_size_bits: u32,
+    pub master_key: Option<String>,
+}
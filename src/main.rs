@@ -2,11 +2,14 @@ use std::sync::Arc;
 use actix_web::{App, HttpServer};
 use actix_web::middleware::Logger;
 use actix_web::web::Data;
+use base64::Engine;
 use log::{Level, log};
 use tokio::fs;
 use crate::api::map;
 use crate::config::ApplicationConfig;
 use crate::repository::data_repository::DataRepository;
+use crate::repository::encryption;
+use crate::repository::storage_backend::{MemoryStorageBackend, SqliteStorageBackend, StorageBackend};
 
 mod repository;
 mod config;
@@ -14,6 +17,7 @@ mod api;
 
 const HOST: &str = "0.0.0.0";
 const PORT: u16 = 8288;
+const TTL_SWEEP_INTERVAL_MS: u64 = 1_000;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -30,11 +34,39 @@ async fn main() -> std::io::Result<()> {
         Err(e) => panic!("Failed to deserialize application configuration with exception: {}", e.to_string())
     };
     let partition_count = config.partition_count;
-    let repository = Arc::new(DataRepository::new(partition_count));
+    let backend: Arc<dyn StorageBackend> = match config.backend.as_str() {
+        "sqlite" => {
+            let path = config.backend_path.as_deref()
+                .unwrap_or_else(|| panic!("backend_path must be set when backend = \"sqlite\""));
+            Arc::new(SqliteStorageBackend::open(path)
+                .unwrap_or_else(|e| panic!("Failed to open sqlite backend with exception: {}", e)))
+        }
+        "memory" => Arc::new(MemoryStorageBackend::new()),
+        other => panic!("Unknown storage backend: {other}")
+    };
+    let master_key = config.master_key.as_deref().map(|encoded| {
+        let decoded = base64::engine::general_purpose::STANDARD_NO_PAD.decode(encoded)
+            .unwrap_or_else(|e| panic!("Failed to decode master_key with exception: {}", e));
+        <[u8; encryption::KEY_LEN]>::try_from(decoded.as_slice())
+            .unwrap_or_else(|_| panic!("master_key must decode to exactly {} bytes", encryption::KEY_LEN))
+    });
+    let repository = Arc::new(DataRepository::with_backend(
+        partition_count, config.regex_partition_count, config.regex_partition_capacity, backend,
+        config.average_chunk_size_bits, master_key));
     std::env::set_var("RUST_LOG", config.log_level);
     std::env::set_var("RUST_BACKTRACE", "1");
     env_logger::init();
     log!(Level::Info, "Partition count: {partition_count}");
+    for partition_index in 0..repository.data_partition_count() {
+        let cloned_repo = repository.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(TTL_SWEEP_INTERVAL_MS));
+            loop {
+                interval.tick().await;
+                cloned_repo.sweep_expired(partition_index).await;
+            }
+        });
+    }
     log!(Level::Info, "Online at {HOST}:{PORT}");
 
     HttpServer::new(move || {
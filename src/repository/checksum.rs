@@ -0,0 +1,93 @@
+use sha1::{Digest as Sha1Digest, Sha1};
+use sha2::{Digest as Sha256Digest, Sha256};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Crc32C,
+    Sha1,
+    Sha256,
+}
+
+impl ChecksumAlgorithm {
+    pub fn from_header_suffix(suffix: &str) -> Option<Self> {
+        match suffix.to_ascii_lowercase().as_str() {
+            "crc32" => Some(Self::Crc32),
+            "crc32c" => Some(Self::Crc32C),
+            "sha1" => Some(Self::Sha1),
+            "sha256" => Some(Self::Sha256),
+            _ => None
+        }
+    }
+    pub fn header_suffix(&self) -> &'static str {
+        match self {
+            Self::Crc32 => "crc32",
+            Self::Crc32C => "crc32c",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256"
+        }
+    }
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Crc32 => 0,
+            Self::Crc32C => 1,
+            Self::Sha1 => 2,
+            Self::Sha256 => 3
+        }
+    }
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Self::Crc32),
+            1 => Some(Self::Crc32C),
+            2 => Some(Self::Sha1),
+            3 => Some(Self::Sha256),
+            _ => None
+        }
+    }
+    pub fn compute(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Crc32 => crc32fast::hash(data).to_be_bytes().to_vec(),
+            Self::Crc32C => crc32c::crc32c(data).to_be_bytes().to_vec(),
+            Self::Sha1 => {
+                let mut hasher = Sha1::new();
+                hasher.update(data);
+                hasher.finalize().to_vec()
+            }
+            Self::Sha256 => {
+                let mut hasher = Sha256::new();
+                hasher.update(data);
+                hasher.finalize().to_vec()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub digest: Vec<u8>,
+}
+
+impl Checksum {
+    pub fn compute(algorithm: ChecksumAlgorithm, data: &[u8]) -> Self {
+        let digest = algorithm.compute(data);
+        Self { algorithm, digest }
+    }
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.algorithm.compute(data) == self.digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compute_round_trips_through_matches_for_every_algorithm() {
+        for algorithm in [ChecksumAlgorithm::Crc32, ChecksumAlgorithm::Crc32C, ChecksumAlgorithm::Sha1, ChecksumAlgorithm::Sha256] {
+            let checksum = Checksum::compute(algorithm, b"hello checksum");
+            assert!(checksum.matches(b"hello checksum"));
+            assert!(!checksum.matches(b"tampered"));
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::repository::chunking;
+use crate::repository::chunking::ChunkingConfig;
+
+/// Content-addressed, reference-counted store for the chunks that
+/// `DataPartition` values are split into. Multiple keys (or multiple chunks
+/// of the same key) that happen to share identical bytes resolve to a single
+/// entry here instead of each paying for their own copy.
+pub(crate) struct ChunkStore {
+    entries: Mutex<HashMap<[u8; 32], (Vec<u8>, u64)>>,
+}
+
+impl ChunkStore {
+    pub fn new() -> Self {
+        Self { entries: Mutex::new(HashMap::new()) }
+    }
+    /// Splits `data` into content-defined chunks, interning each one (bumping
+    /// its refcount if already present), and returns the ordered digest list
+    /// that reconstructs `data` via [`Self::reconstruct`].
+    pub fn intern(&self, data: &[u8], config: &ChunkingConfig) -> Vec<[u8; 32]> {
+        let mut entries = self.entries.lock().unwrap();
+        chunking::split_chunks(data, config).into_iter().map(|(start, end)| {
+            let chunk = &data[start..end];
+            let digest = chunking::digest(chunk);
+            let entry = entries.entry(digest).or_insert_with(|| (chunk.to_vec(), 0));
+            entry.1 += 1;
+            digest
+        }).collect()
+    }
+    /// Decrements the refcount of each digest in `digests`, dropping any
+    /// chunk whose refcount reaches zero. Call whenever a value that
+    /// referenced these chunks is overwritten or removed.
+    pub fn release(&self, digests: &[[u8; 32]]) {
+        let mut entries = self.entries.lock().unwrap();
+        for digest in digests {
+            if let Some(entry) = entries.get_mut(digest) {
+                entry.1 = entry.1.saturating_sub(1);
+                if entry.1 == 0 {
+                    entries.remove(digest);
+                }
+            }
+        }
+    }
+    /// Concatenates the chunk bytes for `digests` in order, or `None` if any
+    /// digest is missing.
+    pub fn reconstruct(&self, digests: &[[u8; 32]]) -> Option<Vec<u8>> {
+        let entries = self.entries.lock().unwrap();
+        let mut value = Vec::new();
+        for digest in digests {
+            value.extend_from_slice(&entries.get(digest)?.0);
+        }
+        Some(value)
+    }
+    /// Clones out the chunk bytes and current refcount for `digest`, for
+    /// serializing a dump straight from live state without re-chunking or
+    /// re-hashing.
+    pub fn get(&self, digest: &[u8; 32]) -> Option<(Vec<u8>, u64)> {
+        self.entries.lock().unwrap().get(digest).cloned()
+    }
+    /// Byte length of the chunk stored under `digest`, without cloning it.
+    pub fn chunk_len(&self, digest: &[u8; 32]) -> Option<usize> {
+        self.entries.lock().unwrap().get(digest).map(|(bytes, _)| bytes.len())
+    }
+}
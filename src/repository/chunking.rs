@@ -0,0 +1,177 @@
+use std::sync::OnceLock;
+use sha2::{Digest, Sha256};
+
+pub const DEFAULT_AVERAGE_CHUNK_SIZE_BITS: u32 = 13;
+const MIN_CHUNK: usize = 1 << 10;
+const MAX_CHUNK: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkingConfig {
+    pub average_chunk_size_bits: u32,
+    pub min_chunk: usize,
+    pub max_chunk: usize,
+}
+
+impl ChunkingConfig {
+    pub fn new(average_chunk_size_bits: u32) -> Self {
+        Self {
+            average_chunk_size_bits,
+            min_chunk: MIN_CHUNK,
+            max_chunk: MAX_CHUNK,
+        }
+    }
+    fn mask(&self) -> u64 {
+        (1u64 << self.average_chunk_size_bits) - 1
+    }
+}
+
+fn gear_table() -> &'static [u64; 256] {
+    static TABLE: OnceLock<[u64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [0u64; 256];
+        let mut state: u64 = 0x9E3779B97F4A7C15;
+        for entry in table.iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *entry = state;
+        }
+        table
+    })
+}
+
+/// Splits `data` into content-defined chunk ranges using a rolling Gear hash:
+/// a boundary falls wherever the low bits of the rolling fingerprint are all
+/// zero, bounded by `min_chunk`/`max_chunk` to avoid pathologically small or
+/// large chunks.
+pub fn split_chunks(data: &[u8], config: &ChunkingConfig) -> Vec<(usize, usize)> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let table = gear_table();
+    let mask = config.mask();
+    let mut boundaries = Vec::new();
+    let mut start = 0usize;
+    let mut fingerprint: u64 = 0;
+    for (i, &byte) in data.iter().enumerate() {
+        fingerprint = (fingerprint << 1).wrapping_add(table[byte as usize]);
+        let len = i + 1 - start;
+        if len >= config.max_chunk || (len >= config.min_chunk && fingerprint & mask == 0) {
+            boundaries.push((start, i + 1));
+            start = i + 1;
+            fingerprint = 0;
+        }
+    }
+    if start < data.len() {
+        boundaries.push((start, data.len()));
+    }
+    boundaries
+}
+
+pub fn digest(chunk: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(chunk);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random_bytes(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect()
+    }
+
+    #[test]
+    fn split_chunks_reconstructs_the_original_data() {
+        let config = ChunkingConfig::new(DEFAULT_AVERAGE_CHUNK_SIZE_BITS);
+        let data = pseudo_random_bytes(100_000, 0x1234_5678_9abc_def0);
+        let mut reconstructed = Vec::new();
+        for (start, end) in split_chunks(&data, &config) {
+            reconstructed.extend_from_slice(&data[start..end]);
+        }
+        assert_eq!(reconstructed, data);
+    }
+
+    #[test]
+    fn identical_content_yields_identical_chunk_digests_regardless_of_prefix() {
+        let config = ChunkingConfig::new(DEFAULT_AVERAGE_CHUNK_SIZE_BITS);
+        // A chunk boundary always resets the rolling fingerprint, so content
+        // appearing right after a boundary chunks identically no matter what
+        // came before it. Build two different prefixes that each end exactly
+        // on a boundary (the first chunk split_chunks finds for some filler
+        // data), then append the same shared content to both.
+        let prefix_a = {
+            let filler = pseudo_random_bytes(30_000, 0xdead_beef_cafe_f00d);
+            let (start, end) = split_chunks(&filler, &config)[0];
+            assert!(end < filler.len(), "filler must contain a real chunk boundary");
+            filler[start..end].to_vec()
+        };
+        let prefix_b = {
+            let filler = pseudo_random_bytes(40_000, 0x0bad_c0de_1234_5678);
+            let (start, end) = split_chunks(&filler, &config)[0];
+            assert!(end < filler.len(), "filler must contain a real chunk boundary");
+            filler[start..end].to_vec()
+        };
+        assert_ne!(prefix_a, prefix_b);
+
+        let shared = pseudo_random_bytes(20_000, 0x5555_aaaa_1111_2222);
+        let shared_digests: Vec<[u8; 32]> = split_chunks(&shared, &config).into_iter()
+            .map(|(s, e)| digest(&shared[s..e]))
+            .collect();
+        assert!(shared_digests.len() > 1, "test needs the shared content to span multiple chunks");
+
+        let data_a = [prefix_a, shared.clone()].concat();
+        let data_b = [prefix_b, shared].concat();
+        let digests_a: Vec<[u8; 32]> = split_chunks(&data_a, &config).into_iter()
+            .map(|(s, e)| digest(&data_a[s..e]))
+            .collect();
+        let digests_b: Vec<[u8; 32]> = split_chunks(&data_b, &config).into_iter()
+            .map(|(s, e)| digest(&data_b[s..e]))
+            .collect();
+
+        // Both values end with exactly the shared content's own digest
+        // sequence, proving the two keys would dedupe onto the same chunks
+        // in the shared content-addressed store despite differing prefixes.
+        assert!(digests_a.ends_with(&shared_digests));
+        assert!(digests_b.ends_with(&shared_digests));
+    }
+
+    #[test]
+    fn chunks_never_fall_below_min_chunk() {
+        // average_chunk_size_bits = 0 makes the mask 0, so `fingerprint &
+        // mask == 0` is always true: the only thing left gating a boundary
+        // is the min_chunk floor, making boundary placement deterministic.
+        let config = ChunkingConfig::new(0);
+        let data = pseudo_random_bytes(3 * MIN_CHUNK + 100, 0x2468_1357_9bdf_eca0);
+        let chunks = split_chunks(&data, &config);
+        assert_eq!(chunks.len(), 4);
+        for (start, end) in &chunks[..3] {
+            assert_eq!(end - start, MIN_CHUNK);
+        }
+        let (start, end) = chunks[3];
+        assert_eq!(end - start, 100);
+    }
+
+    #[test]
+    fn chunks_never_exceed_max_chunk() {
+        // A huge average_chunk_size_bits makes the content-hash condition
+        // practically never fire on data this size, so every boundary here
+        // is forced purely by the max_chunk cap.
+        let config = ChunkingConfig::new(40);
+        let data = pseudo_random_bytes(2 * MAX_CHUNK + 500, 0x1111_2222_3333_4444);
+        let chunks = split_chunks(&data, &config);
+        assert_eq!(chunks.len(), 3);
+        for (start, end) in &chunks[..2] {
+            assert_eq!(end - start, MAX_CHUNK);
+        }
+        let (start, end) = chunks[2];
+        assert_eq!(end - start, 500);
+    }
+}
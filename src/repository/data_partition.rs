@@ -1,14 +1,32 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::sync::atomic::AtomicU32;
+use std::time::Instant;
+use tokio::sync::Notify;
+use crate::repository::checksum::Checksum;
 
 pub(crate) struct DataPartition {
-    pub map: HashMap<String, (Vec<u8>, AtomicU32)>
+    /// Value tuple: (chunk digest list, remaining read budget, TTL deadline, checksum, is_encrypted).
+    /// The stored value is split into content-defined chunks deduplicated in
+    /// the repository's shared `ChunkStore`; reconstruct it with
+    /// `ChunkStore::reconstruct` before use. When `is_encrypted` is set, the
+    /// reconstructed bytes are an `encryption::pack`ed nonce+ciphertext blob.
+    pub map: HashMap<String, (Vec<[u8; 32]>, AtomicU32, Option<Instant>, Option<Checksum>, bool)>,
+    pub versions: HashMap<String, u64>,
+    pub notify: Arc<Notify>,
 }
 
 impl DataPartition{
     pub fn new() -> Self {
         Self {
-            map: HashMap::new()
+            map: HashMap::new(),
+            versions: HashMap::new(),
+            notify: Arc::new(Notify::new()),
         }
     }
-}
\ No newline at end of file
+    pub fn bump_version(&mut self, key: &str) -> u64 {
+        let version = self.versions.entry(key.to_string()).or_insert(0);
+        *version += 1;
+        *version
+    }
+}
@@ -0,0 +1,43 @@
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+
+/// Encrypts `plaintext` under `key` with a freshly generated random nonce,
+/// returning the nonce alongside the ciphertext (which carries the AEAD tag).
+pub fn encrypt(key: &[u8; KEY_LEN], plaintext: &[u8]) -> ([u8; NONCE_LEN], Vec<u8>) {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext)
+        .expect("chacha20poly1305 encryption should not fail for well-formed input");
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    nonce_bytes.copy_from_slice(&nonce);
+    (nonce_bytes, ciphertext)
+}
+
+/// Decrypts `ciphertext` under `key` and `nonce`, failing if the key is wrong
+/// or the data was tampered with.
+pub fn decrypt(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], ciphertext: &[u8]) -> Result<Vec<u8>, ()> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| ())
+}
+
+/// Packs a nonce and its ciphertext into the single opaque blob that gets
+/// stored in `DataPartition` and handed to the storage backend.
+pub fn pack(nonce: &[u8; NONCE_LEN], ciphertext: &[u8]) -> Vec<u8> {
+    let mut packed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    packed.extend_from_slice(nonce);
+    packed.extend_from_slice(ciphertext);
+    packed
+}
+
+/// Reverses [`pack`], returning `None` if `blob` is too short to contain a nonce.
+pub fn unpack(blob: &[u8]) -> Option<([u8; NONCE_LEN], &[u8])> {
+    if blob.len() < NONCE_LEN {
+        return None;
+    }
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&blob[..NONCE_LEN]);
+    Some((nonce, &blob[NONCE_LEN..]))
+}
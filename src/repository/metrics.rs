@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Lifetime operation counters for a [`crate::repository::data_repository::DataRepository`].
+/// Exposed in Prometheus text format via [`render`].
+#[derive(Default)]
+pub struct Metrics {
+    pub reads: AtomicU64,
+    pub cache_hits: AtomicU64,
+    pub cache_misses: AtomicU64,
+    pub writes: AtomicU64,
+    pub removals: AtomicU64,
+    pub read_limit_evictions: AtomicU64,
+    pub regex_cache_hits: AtomicU64,
+    pub regex_cache_misses: AtomicU64,
+    pub match_remove_invocations: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn inc_reads(&self) { self.reads.fetch_add(1, Ordering::Relaxed); }
+    pub fn inc_cache_hits(&self) { self.cache_hits.fetch_add(1, Ordering::Relaxed); }
+    pub fn inc_cache_misses(&self) { self.cache_misses.fetch_add(1, Ordering::Relaxed); }
+    pub fn inc_writes_by(&self, count: u64) { self.writes.fetch_add(count, Ordering::Relaxed); }
+    pub fn inc_removals_by(&self, count: u64) { self.removals.fetch_add(count, Ordering::Relaxed); }
+    pub fn inc_read_limit_evictions(&self) { self.read_limit_evictions.fetch_add(1, Ordering::Relaxed); }
+    pub fn inc_regex_cache_hits(&self) { self.regex_cache_hits.fetch_add(1, Ordering::Relaxed); }
+    pub fn inc_regex_cache_misses(&self) { self.regex_cache_misses.fetch_add(1, Ordering::Relaxed); }
+    pub fn inc_match_remove_invocations(&self) { self.match_remove_invocations.fetch_add(1, Ordering::Relaxed); }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    out.push_str(&format!("# HELP {name} {help}\n"));
+    out.push_str(&format!("# TYPE {name} counter\n"));
+    out.push_str(&format!("{name} {value}\n"));
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: u64) {
+    out.push_str(&format!("# HELP {name} {help}\n"));
+    out.push_str(&format!("# TYPE {name} gauge\n"));
+    out.push_str(&format!("{name} {value}\n"));
+}
+
+fn partition_gauge(out: &mut String, name: &str, help: &str, values: &[u64]) {
+    out.push_str(&format!("# HELP {name} {help}\n"));
+    out.push_str(&format!("# TYPE {name} gauge\n"));
+    for (partition_index, value) in values.iter().enumerate() {
+        out.push_str(&format!("{name}{{partition=\"{partition_index}\"}} {value}\n"));
+    }
+}
+
+/// Renders lifetime counters plus live gauges as Prometheus text-format exposition.
+pub fn render(metrics: &Metrics, total_keys: u64, total_bytes: u64,
+              partition_keys: &[u64], partition_bytes: &[u64]) -> String {
+    let mut out = String::new();
+    counter(&mut out, "rustis_reads_total", "Total number of read operations.",
+            metrics.reads.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_cache_hits_total", "Total number of reads served from the in-memory partition cache.",
+            metrics.cache_hits.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_cache_misses_total", "Total number of reads that fell through to the storage backend.",
+            metrics.cache_misses.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_writes_total", "Total number of write operations.",
+            metrics.writes.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_removals_total", "Total number of key removals.",
+            metrics.removals.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_read_limit_evictions_total", "Total number of keys auto-removed after exhausting their read budget.",
+            metrics.read_limit_evictions.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_regex_cache_hits_total", "Total number of compiled regex cache hits.",
+            metrics.regex_cache_hits.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_regex_cache_misses_total", "Total number of regex compilations due to cache misses.",
+            metrics.regex_cache_misses.load(Ordering::Relaxed));
+    counter(&mut out, "rustis_match_remove_invocations_total", "Total number of pattern-based bulk removal requests.",
+            metrics.match_remove_invocations.load(Ordering::Relaxed));
+    gauge(&mut out, "rustis_keys_total", "Total number of live keys across all partitions.", total_keys);
+    gauge(&mut out, "rustis_bytes_total", "Approximate total size in bytes of all stored values.", total_bytes);
+    partition_gauge(&mut out, "rustis_partition_keys", "Number of keys held by each data partition.", partition_keys);
+    partition_gauge(&mut out, "rustis_partition_bytes", "Approximate size in bytes held by each data partition.", partition_bytes);
+    out
+}
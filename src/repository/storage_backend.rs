@@ -0,0 +1,82 @@
+use rusqlite::OptionalExtension;
+use std::sync::Mutex;
+
+pub trait StorageBackend: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), String>;
+    fn remove(&self, key: &str) -> Result<(), String>;
+    fn iter_keys(&self) -> Result<Vec<String>, String>;
+}
+
+pub struct MemoryStorageBackend;
+
+impl MemoryStorageBackend {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl StorageBackend for MemoryStorageBackend {
+    fn get(&self, _key: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(None)
+    }
+    fn put(&self, _key: &str, _value: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+    fn remove(&self, _key: &str) -> Result<(), String> {
+        Ok(())
+    }
+    fn iter_keys(&self) -> Result<Vec<String>, String> {
+        Ok(Vec::new())
+    }
+}
+
+pub struct SqliteStorageBackend {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+impl SqliteStorageBackend {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let connection = rusqlite::Connection::open(path).map_err(|e| e.to_string())?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS entries (key TEXT PRIMARY KEY, value BLOB NOT NULL)",
+            [],
+        ).map_err(|e| e.to_string())?;
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+}
+
+impl StorageBackend for SqliteStorageBackend {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let connection = self.connection.lock().unwrap();
+        connection.query_row(
+            "SELECT value FROM entries WHERE key = ?1",
+            [key],
+            |row| row.get(0),
+        ).optional().map_err(|e| e.to_string())
+    }
+    fn put(&self, key: &str, value: &[u8]) -> Result<(), String> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO entries (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            rusqlite::params![key, value],
+        ).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    fn remove(&self, key: &str) -> Result<(), String> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute("DELETE FROM entries WHERE key = ?1", rusqlite::params![key])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    fn iter_keys(&self) -> Result<Vec<String>, String> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT key FROM entries").map_err(|e| e.to_string())?;
+        let rows = statement.query_map([], |row| row.get(0)).map_err(|e| e.to_string())?;
+        let mut keys = Vec::new();
+        for row in rows {
+            keys.push(row.map_err(|e| e.to_string())?);
+        }
+        Ok(keys)
+    }
+}